@@ -0,0 +1,294 @@
+//! Lockfile: a reproducible record of a resolver run (`resolver::resolve`),
+//! plus the machinery to keep it in sync as the archive changes instead of
+//! re-solving it from scratch every time.
+//!
+//! `recompute_for_delta` re-resolves only the roots reachable from a single
+//! changed package name and splices the result back into the rest of the
+//! previous lock, which keeps its other entries untouched. It still has to
+//! scan the full `packages` archive as the candidate pool - only the set of
+//! *roots* handed to the solver is narrowed. It has no live hookup of its
+//! own to the DDlog delta stream; `main` drives it by calling it once per
+//! package name present in a transaction's committed delta.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use scfs_ddlog::typedefs::Package;
+
+use crate::resolver::{self, Resolution, ResolveError};
+
+/// One locked package: the name and the concrete version the resolver
+/// chose for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+}
+
+/// The resolver's output, serialized as one `name version` line per
+/// package, sorted by name for a stable diff between runs.
+#[derive(Debug, Clone, Default)]
+pub struct Lockfile {
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    pub fn from_resolution(resolution: &Resolution) -> Self {
+        let mut packages: Vec<_> = resolution
+            .iter()
+            .map(|(name, version)| LockedPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+            })
+            .collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Lockfile { packages }
+    }
+
+    pub fn version_of(&self, name: &str) -> Option<&str> {
+        self.packages
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.version.as_str())
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let packages = text
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let name = parts.next()?.to_string();
+                let version = parts.next()?.to_string();
+                Some(LockedPackage { name, version })
+            })
+            .collect();
+        Lockfile { packages }
+    }
+
+    pub fn render(&self) -> String {
+        self.packages
+            .iter()
+            .map(|p| format!("{} {}\n", p.name, p.version))
+            .collect()
+    }
+}
+
+/// Writes `resolution` to `path` in the lockfile format. Call this after
+/// `transaction_commit_dump_changes` so the lock reflects a committed
+/// archive state.
+pub fn write_lock<P: AsRef<Path>>(path: P, resolution: &Resolution) -> io::Result<()> {
+    write(path, &Lockfile::from_resolution(resolution))
+}
+
+/// Writes an already-built `Lockfile` (e.g. one produced by
+/// `recompute_for_delta`, which has no `Resolution` of its own to hand to
+/// `write_lock`).
+pub fn write<P: AsRef<Path>>(path: P, lockfile: &Lockfile) -> io::Result<()> {
+    fs::write(path, lockfile.render())
+}
+
+pub fn read_lock<P: AsRef<Path>>(path: P) -> io::Result<Lockfile> {
+    Ok(Lockfile::parse(&fs::read_to_string(path)?))
+}
+
+/// Install/remove/upgrade diff between two lockfiles.
+#[derive(Debug, Default, Clone)]
+pub struct LockDiff {
+    pub installed: Vec<LockedPackage>,
+    pub removed: Vec<LockedPackage>,
+    pub upgraded: Vec<(LockedPackage, LockedPackage)>,
+}
+
+fn diff_locks(previous: &Lockfile, next: &Lockfile) -> LockDiff {
+    let before: HashMap<&str, &str> = previous
+        .packages
+        .iter()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .collect();
+    let after: HashMap<&str, &str> = next
+        .packages
+        .iter()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .collect();
+
+    let mut diff = LockDiff::default();
+    for p in &next.packages {
+        match before.get(p.name.as_str()) {
+            None => diff.installed.push(p.clone()),
+            Some(&old_version) if old_version != p.version => diff.upgraded.push((
+                LockedPackage {
+                    name: p.name.clone(),
+                    version: old_version.to_string(),
+                },
+                p.clone(),
+            )),
+            _ => {}
+        }
+    }
+    for p in &previous.packages {
+        if !after.contains_key(p.name.as_str()) {
+            diff.removed.push(p.clone());
+        }
+    }
+    diff
+}
+
+/// Resolves `roots` against `packages`, seeding the solver with `previous`
+/// so already-locked packages keep their version unless their constraints
+/// changed. Returns the new lock alongside its install/remove/upgrade diff
+/// against `previous`.
+pub fn resolve_with_previous(
+    packages: &[Package],
+    roots: &[&str],
+    previous: &Lockfile,
+) -> Result<(Lockfile, LockDiff), ResolveError> {
+    let resolution = resolver::resolve_preferring(packages, roots, previous)?;
+    let next = Lockfile::from_resolution(&resolution);
+    let diff = diff_locks(previous, &next);
+    Ok((next, diff))
+}
+
+/// Given a single `Package` insert/remove delta (as produced by
+/// `apply_updates`) for `changed_package_name`, re-resolves only
+/// `previous`'s roots reachable from it and splices the result back into
+/// the rest of `previous`, so packages outside that reachable set keep
+/// their prior locked version instead of dropping out of the lock.
+///
+/// Only the names outside the reachable set are carried over unchanged;
+/// the reachable set itself comes entirely from `recomputed`, so a
+/// package the re-resolve legitimately drops - because it's no longer
+/// needed, or because `changed_package_name` was removed - actually
+/// leaves the lock instead of being reinstated from `previous`.
+pub fn recompute_for_delta(
+    packages: &[Package],
+    previous: &Lockfile,
+    changed_package_name: &str,
+) -> Result<(Lockfile, LockDiff), ResolveError> {
+    let affected = resolver::dependents_of(packages, changed_package_name);
+    // Roots are the affected names `previous` locked that the archive still
+    // has a package of; a name that left the archive is not demanded again.
+    let roots: Vec<&str> = affected
+        .iter()
+        .filter(|name| previous.version_of(name).is_some())
+        .filter(|name| packages.iter().any(|p| &p.package == *name))
+        .map(String::as_str)
+        .collect();
+    let (recomputed, _) = resolve_with_previous(packages, &roots, previous)?;
+
+    let mut merged_packages: Vec<LockedPackage> = previous
+        .packages
+        .iter()
+        .filter(|p| !affected.contains(&p.name) && recomputed.version_of(&p.name).is_none())
+        .cloned()
+        .collect();
+    merged_packages.extend(recomputed.packages);
+    merged_packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let next = Lockfile {
+        packages: merged_packages,
+    };
+    let diff = diff_locks(previous, &next);
+    Ok((next, diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use scfs_ddlog::typedefs::Dependency;
+
+    use super::*;
+
+    fn package(name: &str, version: &str) -> Package {
+        Package {
+            package: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn locked(name: &str, version: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    fn unconstrained(name: &str) -> Dependency {
+        let mut d = Dependency::default();
+        d.package.push(name.to_string());
+        d.version.push(None.into());
+        d
+    }
+
+    #[test]
+    fn parse_render_round_trips() {
+        let lock = Lockfile {
+            packages: vec![locked("a", "1"), locked("b", "2")],
+        };
+        assert_eq!(Lockfile::parse(&lock.render()).packages, lock.packages);
+    }
+
+    #[test]
+    fn diff_locks_reports_install_remove_upgrade() {
+        let previous = Lockfile {
+            packages: vec![locked("kept", "1"), locked("removed", "1"), locked("upgraded", "1")],
+        };
+        let next = Lockfile {
+            packages: vec![locked("kept", "1"), locked("upgraded", "2"), locked("installed", "1")],
+        };
+
+        let diff = diff_locks(&previous, &next);
+        assert_eq!(diff.installed, vec![locked("installed", "1")]);
+        assert_eq!(diff.removed, vec![locked("removed", "1")]);
+        assert_eq!(diff.upgraded, vec![(locked("upgraded", "1"), locked("upgraded", "2"))]);
+    }
+
+    #[test]
+    fn recompute_for_delta_leaves_the_unreachable_rest_of_the_lock_alone() {
+        let mut app = package("app", "1");
+        let mut dep = Dependency::default();
+        dep.package.push("lib".to_string());
+        dep.version.push(None.into());
+        app.depends.push(dep);
+        let lib = package("lib", "1");
+        let unrelated = package("unrelated", "1");
+        let packages = vec![app, lib, unrelated];
+
+        let previous = Lockfile {
+            packages: vec![locked("app", "1"), locked("lib", "1"), locked("unrelated", "1")],
+        };
+
+        let (next, diff) = recompute_for_delta(&packages, &previous, "lib").unwrap();
+        assert_eq!(next.version_of("unrelated"), Some("1"));
+        assert!(diff.installed.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.upgraded.is_empty());
+    }
+
+    #[test]
+    fn recompute_for_delta_drops_a_package_the_reresolve_no_longer_needs() {
+        // "iface" used to be a real package app depended on directly and
+        // was locked under its own name. In this delta it's gone, replaced
+        // by "provider", which satisfies the same dependency virtually via
+        // Provides - so the re-resolve never assigns anything under the
+        // name "iface" at all. The old lock's "iface" entry must not be
+        // reinstated just because recomputing it produced no such key.
+        let mut app = package("app", "1");
+        app.depends.push(unconstrained("iface"));
+        let mut provider = package("provider", "1");
+        provider.provides.push(unconstrained("iface"));
+        let unrelated = package("unrelated", "1");
+        let packages = vec![app, provider, unrelated];
+
+        let previous = Lockfile {
+            packages: vec![locked("app", "1"), locked("iface", "1"), locked("unrelated", "1")],
+        };
+
+        let (next, diff) = recompute_for_delta(&packages, &previous, "iface").unwrap();
+        assert_eq!(next.version_of("iface"), None);
+        assert_eq!(next.version_of("provider"), Some("1"));
+        assert_eq!(next.version_of("unrelated"), Some("1"));
+        assert_eq!(diff.removed, vec![locked("iface", "1")]);
+    }
+}
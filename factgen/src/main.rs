@@ -23,7 +23,11 @@ use differential_datalog::record::RelIdentifier; // Relation identifier: either
 use differential_datalog::record::UpdCmd; // Dynamically typed representation of DDlog command.
 use differential_datalog::DDlogDump;
 
+mod lockfile;
 mod parse;
+mod resolver;
+mod upgrade;
+mod version;
 
 fn main() -> Result<(), String> {
     env_logger::init();
@@ -63,6 +67,74 @@ fn main() -> Result<(), String> {
         println!("New package: {}", package.package);
     }
 
+    println!("\nResolving and writing lockfile");
+    let resolved_packages: Vec<Package> = new_packages
+        .iter()
+        .map(|(val, _)| unsafe { Package::from_ddvalue_ref(val) }.clone())
+        .collect();
+    // A resolution has to be anchored to something actually requested, not
+    // every package the archive happens to contain - the latter isn't a
+    // meaningful request, and with `solve`'s per-recursion edge expansion
+    // it's exponential over a real-sized pool. Anchor on the first
+    // newly-discovered package as a stand-in for "what was asked for".
+    let roots: Vec<&str> = resolved_packages
+        .first()
+        .map(|p| vec![p.package.as_str()])
+        .unwrap_or_default();
+    let lock_path = "scfs.lock";
+    let outcome = match lockfile::read_lock(lock_path) {
+        // A lock already exists: only re-resolve the part of it reachable
+        // from each package name this transaction's delta touched, rather
+        // than re-solving the whole archive from scratch.
+        Ok(previous) => resolved_packages
+            .iter()
+            .map(|p| p.package.as_str())
+            .try_fold(previous, |previous, name| {
+                lockfile::recompute_for_delta(&resolved_packages, &previous, name).map(
+                    |(next, diff)| {
+                        println!(
+                            "Lock diff for {}: {} installed, {} removed, {} upgraded",
+                            name,
+                            diff.installed.len(),
+                            diff.removed.len(),
+                            diff.upgraded.len()
+                        );
+                        next
+                    },
+                )
+            }),
+        // No lock yet: resolve the anchor package from scratch.
+        Err(_) => resolver::resolve(&resolved_packages, &roots)
+            .map(|resolution| lockfile::Lockfile::from_resolution(&resolution)),
+    };
+    match outcome {
+        Ok(lockfile) => {
+            lockfile::write(lock_path, &lockfile).map_err(|e| e.to_string())?;
+            println!("Wrote lockfile to {}", lock_path);
+        }
+        Err(err) => println!("Resolution failed: {:?}", err.conflicting),
+    }
+
+    println!("\nChecking for compatible upgrades");
+    let installed: Vec<(String, String)> = resolved_packages
+        .iter()
+        .map(|p| (p.package.clone(), p.version.clone()))
+        .collect();
+    for report in upgrade::upgrade_report(&resolved_packages, &installed) {
+        match report.verdict {
+            upgrade::UpgradeVerdict::AlreadyLatest => {
+                println!("{} {}: already latest", report.name, report.installed)
+            }
+            upgrade::UpgradeVerdict::CompatibleUpgrade { to } => {
+                println!("{} {}: can upgrade to {}", report.name, report.installed, to)
+            }
+            upgrade::UpgradeVerdict::ConstraintBreakingOnly { newest, blocked_by } => println!(
+                "{} {}: newest is {} but blocked by {:?}",
+                report.name, report.installed, newest, blocked_by
+            ),
+        }
+    }
+
     let cback: fn(&Record, isize) -> bool = |record, sz| {
         println!("sz = {}", sz);
         true
@@ -10,18 +10,40 @@ use walkdir::WalkDir;
 use scfs_ddlog::typedefs::ddlog_std;
 use scfs_ddlog::typedefs::{Comparator, Dependency, Package};
 
-fn deb_str_to_comparator(s: &str) -> Comparator {
+fn deb_str_to_comparator(s: &str) -> Option<Comparator> {
     match s {
-        "<<" => Comparator::StrictlyEarlier,
-        "<=" => Comparator::EarlierOrEqual,
-        "=" => Comparator::ExactlyEqual,
-        ">=" => Comparator::LaterOrEqual,
-        ">>" => Comparator::StrictlyLater,
-        x => panic!(
-            "Unknown comparator {}. Update `deb_str_to_comparator` and scfs.dl!",
-            x
-        ),
+        "<<" => Some(Comparator::StrictlyEarlier),
+        "<=" => Some(Comparator::EarlierOrEqual),
+        "=" => Some(Comparator::ExactlyEqual),
+        ">=" => Some(Comparator::LaterOrEqual),
+        ">>" => Some(Comparator::StrictlyLater),
+        x => {
+            error!("Unknown comparator '{}' in a relationship field; treating as unconstrained. Update `deb_str_to_comparator` and scfs.dl!", x);
+            None
+        }
+    }
+}
+
+/// Splits a parenthesized version clause's contents (e.g. `>= 1.0` or the
+/// equally valid, space-free `>=1.0`) into `(comparator, version)`. Parses
+/// defensively: a relationship field comes from a real-world `.deb` we
+/// don't control, so a clause apt would accept but we fail to recognize
+/// should be logged and dropped, never a panic.
+fn parse_version_constraint(version_line: &str) -> Option<(Comparator, String)> {
+    let op_end = version_line
+        .find(|c: char| !matches!(c, '<' | '=' | '>'))
+        .unwrap_or(version_line.len());
+    let (op, version) = version_line.split_at(op_end);
+    let cmp = deb_str_to_comparator(op.trim())?;
+    let version = version.trim();
+    if version.is_empty() {
+        error!(
+            "No version found in version constraint '{}'; treating as unconstrained",
+            version_line
+        );
+        return None;
     }
+    Some((cmp, version.to_string()))
 }
 
 /// Potential tags that can appear in our .deb packets
@@ -220,6 +242,62 @@ impl From<&str> for Tags {
     }
 }
 
+/// Parses a comma/`|`-separated relationship field (`Depends`, `Conflicts`,
+/// `Provides`, ...) into `Dependency` values.
+///
+/// Parses a string like this: "libc6 (>= 2.29), libqt5gui5 (>= 5.5) |
+/// libqt5gui5-gles (>= 5.5)" (e.g., requires libc6 AND (libqt5gui5 OR
+/// libqt5gui5-gles))
+///
+/// First each dependency is split by `,` for the ANDs, then split by `|` for
+/// the ORs. The ORs just extend the Vec<> fields within a single
+/// Dependency.
+///
+/// More about version constraints:
+/// https://www.debian.org/doc/debian-policy/ch-controlfields.html#version
+/// https://www.debian.org/doc/debian-policy/ch-relationships.html
+fn parse_dependency_field(line: &str) -> ddlog_std::Vec<Dependency> {
+    let mut result = ddlog_std::Vec::new();
+
+    for or_dependency in line.split(',').collect::<Vec<&str>>() {
+        if or_dependency.trim().is_empty() {
+            continue;
+        }
+
+        let mut d: Dependency = Default::default();
+
+        for dependency in or_dependency.split("|").collect::<Vec<&str>>() {
+            match dependency.rfind("(") {
+                Some(mid) => {
+                    let (name, version_line) = dependency.split_at(mid);
+                    let name = name.trim(); // Skip space
+                    // Trim whitespace first: a non-last OR alternative still
+                    // has the space before the `|` separator at this point,
+                    // so trim_end_matches(')') would otherwise see that
+                    // space, not the closing paren, and leave it dangling.
+                    let version_line = version_line
+                        .trim()
+                        .trim_start_matches('(')
+                        .trim_end_matches(')')
+                        .trim();
+
+                    d.package.push(name.to_string());
+                    d.version.push(parse_version_constraint(version_line).into());
+                }
+                None => {
+                    d.package.push(dependency.trim().to_string());
+                    // No version constraint
+                    d.version.push(None.into());
+                }
+            }
+        }
+
+        result.push(d);
+    }
+
+    result
+}
+
 pub fn parse_package<P: AsRef<Path>>(path: &P) -> Package {
     let file = std::fs::File::open(path).unwrap();
     let mut pkg = DebPkg::parse(file).unwrap();
@@ -253,69 +331,39 @@ pub fn parse_package<P: AsRef<Path>>(path: &P) -> Package {
                 p.original_maintainer = control.get(tag.field_name()).map(|t| t.to_string()).into()
             }
             Tags::Depends => {
-                // Parses a string like this: "libc6 (>= 2.29), libqt5gui5 (>=
-                // 5.5) | libqt5gui5-gles (>= 5.5)" (e.g., requires libc6 AND
-                // (libqt5gui5 OR libqt5gui5-gles))
-                //
-                // First each dependency is split by `,` for the ANDs, then
-                // split by `|` for the ORs. The ORs just extend the Vec<>
-                // fields within a single Dependency.
-                //
-                // More about version constraints:
-                // https://www.debian.org/doc/debian-policy/ch-controlfields.html#version
-                // https://www.debian.org/doc/debian-policy/ch-relationships.html
-                p.depends = ddlog_std::Vec::new();
-                let dependencies_line = control.get(tag.field_name()).unwrap_or("");
-
-                for or_dependency in dependencies_line.split(',').collect::<Vec<&str>>() {
-                    let mut d: Dependency = Default::default();
-
-                    for dependency in or_dependency.split("|").collect::<Vec<&str>>() {
-                        match dependency.rfind("(") {
-                            Some(mid) => {
-                                let (name, version_line) = dependency.split_at(mid);
-                                let name = name.trim(); // Skip space
-                                let version_line = version_line
-                                    .trim_start_matches('(')
-                                    .trim_end_matches(')')
-                                    .trim();
-
-                                match version_line.rfind(' ') {
-                                    Some(mid) => {
-                                        let (vconstraint, version) = version_line.split_at(mid);
-                                        d.package.push(name.to_string());
-                                        d.version.push(
-                                            Some(
-                                                (
-                                                    deb_str_to_comparator(vconstraint.trim()),
-                                                    version.trim().to_string(),
-                                                )
-                                                    .into(),
-                                            )
-                                            .into(),
-                                        );
-                                    }
-                                    None => {
-                                        unreachable!(
-                                        "We should find some version constraint (==, >= etc.) in: {}",
-                                        version_line
-                                    );
-                                    }
-                                }
-                            }
-                            None => {
-                                d.package.push(dependency.trim().to_string());
-                                // No version constraint
-                                d.version.push(None.into());
-                            }
-                        }
-                    }
-
-                    p.depends.push(d);
-                }
+                p.depends = parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
             }
+            Tags::PreDepends => {
+                p.pre_depends =
+                    parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
+            }
+            Tags::Conflicts => {
+                p.conflicts = parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
+            }
+            Tags::Breaks => {
+                p.breaks = parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
+            }
+            Tags::Recommends => {
+                p.recommends =
+                    parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
+            }
+            Tags::Suggests => {
+                p.suggests = parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
+            }
+            // `Provides` shares the Depends grammar, except its entries are
+            // virtual-package names rather than real ones: a `Depends` on
+            // `X` is satisfiable by any installed package that `Provides: X`
+            // (optionally versioned, `Provides: X (= 1.2)`).
+            Tags::Provides => {
+                p.provides = parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
+            }
+            // `replaces` moved from `Option<String>` to a `Dependency` vec
+            // here, matching `conflicts`/`breaks`/`provides`/`pre_depends`/
+            // `recommends`/`suggests` above. That's a breaking change to the
+            // generated `Package` type, so `scfs.dl` has to declare these
+            // fields with the matching shape or this won't compile.
             Tags::Replaces => {
-                p.replaces = control.get(tag.field_name()).map(|t| t.to_string()).into();
+                p.replaces = parse_dependency_field(control.get(tag.field_name()).unwrap_or(""));
             }
             Tags::Section => {
                 p.section = control.get(tag.field_name()).map(|t| t.to_string()).into();
@@ -358,3 +406,114 @@ pub fn parse_packages(root: PathBuf) -> Result<ddlog_std::Vec<Package>, String>
 
     Ok(packages.into())
 }
+
+/// Fuzzes `parse_dependency_field`'s grammar with randomly generated
+/// relationship lines (random names, Debian versions with epochs/`~`, AND/OR
+/// groups and version constraints) and asserts that rendering a generated
+/// structure to control-file text and parsing it back reproduces the
+/// original `Dependency` structure.
+#[cfg(test)]
+mod proptests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn comparator_to_deb_str(cmp: Comparator) -> &'static str {
+        match cmp {
+            Comparator::StrictlyEarlier => "<<",
+            Comparator::EarlierOrEqual => "<=",
+            Comparator::ExactlyEqual => "=",
+            Comparator::LaterOrEqual => ">=",
+            Comparator::StrictlyLater => ">>",
+        }
+    }
+
+    fn package_name() -> impl Strategy<Value = String> {
+        "[a-z][a-z0-9.+-]{1,12}"
+    }
+
+    fn comparator() -> impl Strategy<Value = Comparator> {
+        prop_oneof![
+            Just(Comparator::StrictlyEarlier),
+            Just(Comparator::EarlierOrEqual),
+            Just(Comparator::ExactlyEqual),
+            Just(Comparator::LaterOrEqual),
+            Just(Comparator::StrictlyLater),
+        ]
+    }
+
+    fn deb_version() -> impl Strategy<Value = String> {
+        (
+            proptest::option::of(0u32..5),
+            1u32..100,
+            0u32..100,
+            proptest::option::of("[a-z]{1,4}"),
+            proptest::option::of(1u32..20),
+        )
+            .prop_map(|(epoch, major, minor, tilde_suffix, revision)| {
+                let mut v = String::new();
+                if let Some(epoch) = epoch {
+                    v.push_str(&format!("{}:", epoch));
+                }
+                v.push_str(&format!("{}.{}", major, minor));
+                if let Some(suffix) = tilde_suffix {
+                    v.push('~');
+                    v.push_str(&suffix);
+                }
+                if let Some(revision) = revision {
+                    v.push_str(&format!("-{}", revision));
+                }
+                v
+            })
+    }
+
+    type Alternative = (String, Option<(Comparator, String)>);
+
+    fn alternative() -> impl Strategy<Value = Alternative> {
+        (
+            package_name(),
+            proptest::option::of((comparator(), deb_version())),
+        )
+    }
+
+    fn dependency_line() -> impl Strategy<Value = Vec<Vec<Alternative>>> {
+        prop::collection::vec(prop::collection::vec(alternative(), 1..3), 0..5)
+    }
+
+    fn render(groups: &[Vec<Alternative>]) -> String {
+        groups
+            .iter()
+            .map(|alts| {
+                alts.iter()
+                    .map(|(name, constraint)| match constraint {
+                        Some((cmp, version)) => {
+                            format!("{} ({} {})", name, comparator_to_deb_str(*cmp), version)
+                        }
+                        None => name.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    proptest! {
+        #[test]
+        fn parse_dependency_field_round_trips(groups in dependency_line()) {
+            let rendered = render(&groups);
+            let parsed = parse_dependency_field(&rendered);
+
+            prop_assert_eq!(parsed.len(), groups.len());
+            for (dep, expected) in parsed.iter().zip(groups.iter()) {
+                let actual: Vec<Alternative> = dep
+                    .package
+                    .iter()
+                    .cloned()
+                    .zip(dep.version.iter().cloned().map(Into::into))
+                    .collect();
+                prop_assert_eq!(actual, expected.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,657 @@
+//! Dependency resolution: given a set of root package requests, computes a
+//! transitive, version-consistent install set over the archive loaded by
+//! `parse::parse_packages`.
+//!
+//! Modeled after a cargo-style backtracking solver: we keep a stack of
+//! partial assignments, and at each step pick an unresolved dependency edge,
+//! enumerate candidate versions (newest first) that satisfy every
+//! currently-active constraint, and recurse. `Dependency` entries with more
+//! than one `package`/`version` alternative are OR-groups (`a | b`); we try
+//! each alternative in order before giving up on the edge.
+
+use std::collections::{HashMap, HashSet};
+
+use scfs_ddlog::typedefs::{Comparator, Dependency, Package};
+
+use crate::version;
+
+/// The resolved install set: one concrete version chosen per package name.
+#[derive(Debug, Default, Clone)]
+pub struct Resolution {
+    chosen: HashMap<String, String>,
+}
+
+impl Resolution {
+    /// Enumerates the resolved `(name, chosen_version)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.chosen
+            .iter()
+            .map(|(name, version)| (name.as_str(), version.as_str()))
+    }
+
+    pub fn version_of(&self, name: &str) -> Option<&str> {
+        self.chosen.get(name).map(|v| v.as_str())
+    }
+}
+
+/// Resolution failed: no combination of candidate versions satisfied every
+/// active constraint. `conflicting` holds the reason for the last failure
+/// the solver hit while backtracking (an unsatisfied constraint, a
+/// `Conflicts`/`Breaks` clash, or an edge with no candidate), for
+/// diagnostics; it is not reduced to a minimal conflicting set.
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    pub conflicting: Vec<String>,
+}
+
+/// One alternative of a dependency edge: a package name plus an optional
+/// version constraint.
+#[derive(Clone)]
+pub(crate) struct Alternative {
+    pub(crate) name: String,
+    pub(crate) constraint: Option<(Comparator, String)>,
+}
+
+pub(crate) fn alternatives(dep: &Dependency) -> Vec<Alternative> {
+    dep.package
+        .iter()
+        .zip(dep.version.iter())
+        .map(|(name, constraint)| Alternative {
+            name: name.clone(),
+            constraint: constraint.clone().into(),
+        })
+        .collect()
+}
+
+/// Candidate versions for every package name, sorted newest first, plus an
+/// index of which packages `Provides` a given (virtual) name.
+struct Archive<'a> {
+    by_name: HashMap<&'a str, Vec<&'a Package>>,
+    providers_of: HashMap<String, Vec<&'a Package>>,
+}
+
+impl<'a> Archive<'a> {
+    fn build(packages: &'a [Package]) -> Self {
+        let mut by_name: HashMap<&str, Vec<&Package>> = HashMap::new();
+        let mut providers_of: HashMap<String, Vec<&Package>> = HashMap::new();
+        for p in packages {
+            by_name.entry(p.package.as_str()).or_default().push(p);
+            for provided in p.provides.iter() {
+                for alt in alternatives(provided) {
+                    providers_of.entry(alt.name).or_default().push(p);
+                }
+            }
+        }
+        for candidates in by_name.values_mut() {
+            candidates.sort_by(|a, b| version::compare(&b.version, &a.version));
+        }
+        for candidates in providers_of.values_mut() {
+            candidates.sort_by(|a, b| version::compare(&b.version, &a.version));
+        }
+        Archive {
+            by_name,
+            providers_of,
+        }
+    }
+
+    /// Like `build`, but for every name `previous` has a locked version of,
+    /// that version (if still present) is moved to the front of its
+    /// candidate list, ahead of newer ones.
+    fn build_preferring(packages: &'a [Package], previous: &crate::lockfile::Lockfile) -> Self {
+        let mut archive = Self::build(packages);
+        for (name, candidates) in archive.by_name.iter_mut() {
+            let Some(locked_version) = previous.version_of(name) else {
+                continue;
+            };
+            if let Some(pos) = candidates.iter().position(|p| p.version == locked_version) {
+                let preferred = candidates.remove(pos);
+                candidates.insert(0, preferred);
+            }
+        }
+        archive
+    }
+
+    fn candidates(&self, name: &str) -> &[&'a Package] {
+        self.by_name.get(name).map_or(&[], |v| v.as_slice())
+    }
+
+    fn providers(&self, name: &str) -> &[&'a Package] {
+        self.providers_of.get(name).map_or(&[], |v| v.as_slice())
+    }
+}
+
+/// The dependency edges of a package that the resolver must satisfy:
+/// `Depends` and `Pre-Depends` both have to hold for the package to be
+/// installable.
+pub(crate) fn edges(package: &Package) -> impl Iterator<Item = &Dependency> {
+    package.depends.iter().chain(package.pre_depends.iter())
+}
+
+/// Groups every loaded package by name, sorted newest-version-first, for
+/// callers outside the resolver (e.g. the upgrade query) that need the same
+/// "what versions exist" view without running a resolution.
+pub(crate) fn candidates_by_name(packages: &[Package]) -> HashMap<String, Vec<&Package>> {
+    let mut by_name: HashMap<String, Vec<&Package>> = HashMap::new();
+    for p in packages {
+        by_name.entry(p.package.clone()).or_default().push(p);
+    }
+    for candidates in by_name.values_mut() {
+        candidates.sort_by(|a, b| version::compare(&b.version, &a.version));
+    }
+    by_name
+}
+
+/// Does `provider` satisfy `alt` via a `Provides` entry? An unversioned
+/// `Provides: X` only satisfies an unversioned dependency on `X`; a
+/// versioned `Provides: X (= 1.2)` satisfies a dependency on `X` whose
+/// constraint that exact version also satisfies.
+fn provides(provider: &Package, alt: &Alternative) -> bool {
+    provider.provides.iter().any(|provided| {
+        alternatives(provided).iter().any(|p| {
+            if p.name != alt.name {
+                return false;
+            }
+            match (&p.constraint, &alt.constraint) {
+                (_, None) => true,
+                (Some((Comparator::ExactlyEqual, provided_version)), Some((cmp, bound))) => {
+                    version::satisfies(provided_version, *cmp, bound)
+                }
+                (None, Some(_)) => false,
+                (Some(_), Some(_)) => false,
+            }
+        })
+    })
+}
+
+/// Does `candidate` conflict, via `Conflicts`/`Breaks` in either
+/// direction, with anything already committed in `assigned`? Returns a
+/// diagnostic description of the first conflict found, if any.
+fn conflict_with_assigned(candidate: &Package, assigned: &HashMap<String, &Package>) -> Option<String> {
+    for (via, field) in [("Conflicts", &candidate.conflicts), ("Breaks", &candidate.breaks)] {
+        for dep in field.iter() {
+            for alt in alternatives(dep) {
+                if let Some(other) = assigned.get(alt.name.as_str()) {
+                    if satisfies_alternative(&other.version, &alt) {
+                        return Some(format!(
+                            "{} {} ({} {}) conflicts with already-assigned {} {}",
+                            candidate.package,
+                            candidate.version,
+                            via,
+                            describe(&alt),
+                            other.package,
+                            other.version
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    for other in assigned.values() {
+        for (via, field) in [("Conflicts", &other.conflicts), ("Breaks", &other.breaks)] {
+            for dep in field.iter() {
+                for alt in alternatives(dep) {
+                    if alt.name == candidate.package && satisfies_alternative(&candidate.version, &alt) {
+                        return Some(format!(
+                            "already-assigned {} {} ({} {}) conflicts with {} {}",
+                            other.package,
+                            other.version,
+                            via,
+                            describe(&alt),
+                            candidate.package,
+                            candidate.version
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+struct Solver<'a> {
+    archive: Archive<'a>,
+    /// Committed choices so far: name -> chosen package.
+    assigned: HashMap<String, &'a Package>,
+    /// `(assignment signature, pending edges)` pairs already proven
+    /// unsatisfiable, so we don't re-explore the same dead end while
+    /// backtracking. Both halves matter: the same assignment can be
+    /// reached with different outstanding `pending` edges, and a
+    /// key on the assignment alone would wrongly reuse one edge set's
+    /// failure for another, completable one.
+    conflict_cache: HashSet<(Vec<(String, String)>, Vec<String>)>,
+}
+
+impl<'a> Solver<'a> {
+    fn signature(&self) -> Vec<(String, String)> {
+        let mut sig: Vec<_> = self
+            .assigned
+            .iter()
+            .map(|(n, p)| (n.clone(), p.version.clone()))
+            .collect();
+        sig.sort();
+        sig
+    }
+
+    fn cache_key(&self, pending: &[Dependency]) -> (Vec<(String, String)>, Vec<String>) {
+        (self.signature(), pending.iter().map(describe_edge).collect())
+    }
+
+    /// Tries to satisfy `pending` edges given the current assignment.
+    /// On failure, returns the reason for the last failed attempt.
+    fn solve(&mut self, pending: &[Dependency]) -> Result<(), Vec<String>> {
+        let Some((edge, rest)) = pending.split_first() else {
+            return Ok(());
+        };
+
+        let key = self.cache_key(pending);
+        if self.conflict_cache.contains(&key) {
+            return Err(vec!["known-unsatisfiable partial assignment".to_string()]);
+        }
+
+        let mut last_err = Vec::new();
+        for alt in alternatives(edge) {
+            if let Some(existing) = self.assigned.get(alt.name.as_str()) {
+                // Already decided: the existing choice must satisfy this edge too.
+                if satisfies_alternative(&existing.version, &alt) {
+                    match self.solve(rest) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => last_err = e,
+                    }
+                } else {
+                    last_err = vec![format!(
+                        "{} = {} does not satisfy {}",
+                        alt.name, existing.version, describe(&alt)
+                    )];
+                }
+                continue;
+            }
+
+            // Already satisfied through a virtual package some other
+            // assignment provides - no new assignment needed.
+            if self.assigned.values().any(|p| provides(p, &alt)) {
+                match self.solve(rest) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => last_err = e,
+                }
+                continue;
+            }
+
+            let candidates = self.archive.candidates(&alt.name).to_vec();
+            for candidate in candidates {
+                if !satisfies_alternative(&candidate.version, &alt) {
+                    continue;
+                }
+                if let Some(reason) = conflict_with_assigned(candidate, &self.assigned) {
+                    last_err = vec![reason];
+                    continue;
+                }
+                self.assigned.insert(alt.name.clone(), candidate);
+
+                let mut next = Vec::with_capacity(rest.len() + edges(candidate).count());
+                next.extend(rest.iter().cloned());
+                next.extend(edges(candidate).cloned());
+
+                match self.solve(&next) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        self.assigned.remove(alt.name.as_str());
+                        last_err = e;
+                    }
+                }
+            }
+
+            // No real package named `alt.name` works; try packages that
+            // `Provides` it instead, assigned under their own name.
+            let providers = self.archive.providers(&alt.name).to_vec();
+            for provider in providers {
+                if self.assigned.contains_key(provider.package.as_str())
+                    || !provides(provider, &alt)
+                {
+                    continue;
+                }
+                if let Some(reason) = conflict_with_assigned(provider, &self.assigned) {
+                    last_err = vec![reason];
+                    continue;
+                }
+                self.assigned.insert(provider.package.clone(), provider);
+
+                let mut next = Vec::with_capacity(rest.len() + edges(provider).count());
+                next.extend(rest.iter().cloned());
+                next.extend(edges(provider).cloned());
+
+                match self.solve(&next) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        self.assigned.remove(provider.package.as_str());
+                        last_err = e;
+                    }
+                }
+            }
+        }
+
+        if last_err.is_empty() {
+            last_err = vec![format!("no candidate satisfies {}", describe_edge(edge))];
+        }
+        self.conflict_cache.insert(key);
+        Err(last_err)
+    }
+}
+
+pub(crate) fn satisfies_alternative(candidate_version: &str, alt: &Alternative) -> bool {
+    match &alt.constraint {
+        Some((cmp, bound)) => version::satisfies(candidate_version, *cmp, bound),
+        None => true,
+    }
+}
+
+pub(crate) fn describe(alt: &Alternative) -> String {
+    match &alt.constraint {
+        Some((cmp, bound)) => format!("{} ({:?} {})", alt.name, cmp, bound),
+        None => alt.name.clone(),
+    }
+}
+
+pub(crate) fn describe_edge(edge: &Dependency) -> String {
+    alternatives(edge)
+        .iter()
+        .map(describe)
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn resolve_with_archive(archive: Archive<'_>, roots: &[&str]) -> Result<Resolution, ResolveError> {
+    let mut solver = Solver {
+        archive,
+        assigned: HashMap::new(),
+        conflict_cache: HashSet::new(),
+    };
+
+    let root_edges: Vec<Dependency> = roots
+        .iter()
+        .map(|name| {
+            let mut d = Dependency::default();
+            d.package.push(name.to_string());
+            d.version.push(None.into());
+            d
+        })
+        .collect();
+
+    solver
+        .solve(&root_edges)
+        .map(|()| Resolution {
+            chosen: solver
+                .assigned
+                .into_iter()
+                .map(|(n, p)| (n, p.version.clone()))
+                .collect(),
+        })
+        .map_err(|conflicting| ResolveError { conflicting })
+}
+
+/// Resolves `roots` (plain package names, unconstrained) against `packages`,
+/// choosing exactly one concrete version per transitively-required package.
+pub fn resolve(packages: &[Package], roots: &[&str]) -> Result<Resolution, ResolveError> {
+    resolve_with_archive(Archive::build(packages), roots)
+}
+
+/// Like `resolve`, but candidates already present in `previous` are tried
+/// first, so that unaffected packages keep their previously-locked version
+/// instead of drifting to a newer one purely because it exists.
+pub fn resolve_preferring(
+    packages: &[Package],
+    roots: &[&str],
+    previous: &crate::lockfile::Lockfile,
+) -> Result<Resolution, ResolveError> {
+    resolve_with_archive(Archive::build_preferring(packages, previous), roots)
+}
+
+/// The set of package names whose `Depends`/`Pre-Depends` closure (at any
+/// version present in the archive) reaches `changed` - i.e. everything an
+/// incremental re-resolve after a `Package` delta on `changed` needs to
+/// revisit.
+pub fn dependents_of(packages: &[Package], changed: &str) -> HashSet<String> {
+    let mut dependents_by_target: HashMap<String, HashSet<String>> = HashMap::new();
+    for p in packages {
+        for dep in edges(p) {
+            for alt in alternatives(dep) {
+                dependents_by_target
+                    .entry(alt.name)
+                    .or_default()
+                    .insert(p.package.clone());
+            }
+        }
+    }
+
+    let mut reachable = HashSet::new();
+    let mut frontier = vec![changed.to_string()];
+    reachable.insert(changed.to_string());
+    while let Some(name) = frontier.pop() {
+        if let Some(next) = dependents_by_target.get(&name) {
+            for dependent in next {
+                if reachable.insert(dependent.clone()) {
+                    frontier.push(dependent.clone());
+                }
+            }
+        }
+    }
+    reachable
+}
+
+/// A pair of packages in the loaded archive that cannot be co-installed
+/// because one `Conflicts`/`Breaks` a version of the other that is actually
+/// present.
+#[derive(Debug, Clone)]
+pub struct ConflictingPair {
+    pub package: String,
+    pub package_version: String,
+    pub other: String,
+    pub other_version: String,
+    pub via: &'static str,
+}
+
+/// Standalone conflict query over the whole archive (independent of any
+/// resolution): every `Conflicts`/`Breaks` edge that some present version of
+/// the named package actually satisfies.
+pub fn detect_conflicts(packages: &[Package]) -> Vec<ConflictingPair> {
+    let archive = Archive::build(packages);
+    let mut found = Vec::new();
+
+    for p in packages {
+        for (via, field) in [("Conflicts", &p.conflicts), ("Breaks", &p.breaks)] {
+            for dep in field.iter() {
+                for alt in alternatives(dep) {
+                    for other in archive.candidates(&alt.name) {
+                        let same_package =
+                            other.package == p.package && other.version == p.version;
+                        if same_package || !satisfies_alternative(&other.version, &alt) {
+                            continue;
+                        }
+                        found.push(ConflictingPair {
+                            package: p.package.clone(),
+                            package_version: p.version.clone(),
+                            other: other.package.clone(),
+                            other_version: other.version.clone(),
+                            via,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// Fuzzes the resolver with randomly generated package graphs (random
+/// names, each with several published versions, OR-groups and version
+/// constraints on `Depends` edges, plus `Conflicts` edges among the
+/// generated names) and asserts the invariants a cargo-style resolver is
+/// expected to hold: every chosen version satisfies every edge that
+/// selected it, no two chosen packages conflict, and resolution is
+/// deterministic regardless of the input package order.
+#[cfg(test)]
+mod proptests {
+    use std::collections::HashMap;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn package_name() -> impl Strategy<Value = String> {
+        "[a-z]{1,3}"
+    }
+
+    /// Version numbers are plain small integers rendered as strings, so
+    /// `version::compare` orders them the obvious way and constraints drawn
+    /// from the same `1..4` domain have a real chance of both matching and
+    /// not matching a generated version.
+    fn versions() -> impl Strategy<Value = Vec<String>> {
+        prop::collection::hash_set(1u32..4, 1..4).prop_map(|versions| {
+            let mut versions: Vec<u32> = versions.into_iter().collect();
+            versions.sort_unstable();
+            versions.into_iter().map(|v| v.to_string()).collect()
+        })
+    }
+
+    fn comparator() -> impl Strategy<Value = Comparator> {
+        prop_oneof![
+            Just(Comparator::EarlierOrEqual),
+            Just(Comparator::ExactlyEqual),
+            Just(Comparator::LaterOrEqual),
+        ]
+    }
+
+    /// One OR-group alternative: an index into the generated name list plus
+    /// an optional version constraint.
+    fn edge_target(n: usize) -> impl Strategy<Value = (usize, Option<(Comparator, u32)>)> {
+        (0..n, proptest::option::of((comparator(), 1u32..4)))
+    }
+
+    fn or_group(n: usize) -> impl Strategy<Value = Vec<(usize, Option<(Comparator, u32)>)>> {
+        prop::collection::vec(edge_target(n), 1..3)
+    }
+
+    fn dependency_from(names: &[String], targets: Vec<(usize, Option<(Comparator, u32)>)>) -> Dependency {
+        let mut d = Dependency::default();
+        for (j, constraint) in targets {
+            d.package.push(names[j].clone());
+            d.version
+                .push(constraint.map(|(cmp, v)| (cmp, v.to_string())).into());
+        }
+        d
+    }
+
+    /// A handful of distinctly-named packages, each with 1-3 published
+    /// versions, `Depends` edges (possibly OR-groups, possibly versioned)
+    /// and `Conflicts` edges, all among the generated names.
+    fn package_graph() -> impl Strategy<Value = Vec<Package>> {
+        prop::collection::hash_set(package_name(), 2..6).prop_flat_map(|names| {
+            let names: Vec<String> = names.into_iter().collect();
+            let n = names.len();
+            (
+                prop::collection::vec(versions(), n),
+                prop::collection::vec(prop::collection::vec(or_group(n), 0..3), n),
+                prop::collection::vec(prop::collection::vec(edge_target(n), 0..2), n),
+            )
+                .prop_map(move |(versions_by_index, depends_by_index, conflicts_by_index)| {
+                    let mut packages = Vec::new();
+                    for i in 0..n {
+                        for version in &versions_by_index[i] {
+                            let mut p = Package {
+                                package: names[i].clone(),
+                                version: version.clone(),
+                                ..Default::default()
+                            };
+
+                            for group in &depends_by_index[i] {
+                                let mut targets = group.clone();
+                                targets.retain(|(j, _)| *j != i);
+                                targets.sort_by_key(|(j, _)| *j);
+                                targets.dedup_by_key(|(j, _)| *j);
+                                if targets.is_empty() {
+                                    continue;
+                                }
+                                p.depends.push(dependency_from(&names, targets));
+                            }
+
+                            for (j, constraint) in conflicts_by_index[i].iter().cloned() {
+                                if j == i {
+                                    continue;
+                                }
+                                p.conflicts.push(dependency_from(&names, vec![(j, constraint)]));
+                            }
+
+                            packages.push(p);
+                        }
+                    }
+                    packages
+                })
+        })
+    }
+
+    fn root_names(packages: &[Package]) -> Vec<&str> {
+        let mut names: Vec<&str> = packages.iter().map(|p| p.package.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn sorted_pairs(resolution: &Resolution) -> Vec<(String, String)> {
+        let mut v: Vec<_> = resolution
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+        v.sort();
+        v
+    }
+
+    proptest! {
+        #[test]
+        fn resolution_satisfies_every_edge(packages in package_graph()) {
+            let roots = root_names(&packages);
+            if let Ok(resolution) = resolve(&packages, &roots) {
+                let chosen: HashMap<&str, &str> = resolution.iter().collect();
+
+                for p in &packages {
+                    if chosen.get(p.package.as_str()) != Some(&p.version.as_str()) {
+                        continue;
+                    }
+                    for dep in edges(p) {
+                        let satisfied = alternatives(dep).iter().any(|alt| {
+                            chosen
+                                .get(alt.name.as_str())
+                                .is_some_and(|v| satisfies_alternative(v, alt))
+                        });
+                        prop_assert!(satisfied);
+                    }
+
+                    for field in [&p.conflicts, &p.breaks] {
+                        for dep in field.iter() {
+                            for alt in alternatives(dep) {
+                                let violates = chosen
+                                    .get(alt.name.as_str())
+                                    .is_some_and(|v| satisfies_alternative(v, &alt));
+                                prop_assert!(!violates);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn resolution_is_order_independent(packages in package_graph()) {
+            let roots = root_names(&packages);
+            let mut reversed = packages.clone();
+            reversed.reverse();
+
+            let forward = resolve(&packages, &roots);
+            let backward = resolve(&reversed, &roots);
+
+            prop_assert_eq!(forward.is_ok(), backward.is_ok());
+            if let (Ok(forward), Ok(backward)) = (forward, backward) {
+                prop_assert_eq!(sorted_pairs(&forward), sorted_pairs(&backward));
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! "Upgrade to latest compatible" query, mirroring cargo-edit's upgrade
+//! logic: for each currently-installed `(name, version)`, report the
+//! newest version in the loaded archive that is still compatible with
+//! every other installed package's active `Depends` constraints on it.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use scfs_ddlog::typedefs::Package;
+
+use crate::{resolver, version};
+
+/// The upgrade verdict for one installed package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpgradeVerdict {
+    /// No version present in the archive is newer than `installed` (this
+    /// includes a name the archive has no versions of at all).
+    AlreadyLatest,
+    /// A newer version exists and adopting it would not violate any active
+    /// constraint.
+    CompatibleUpgrade { to: String },
+    /// Newer versions exist, but every one of them is blocked by some
+    /// constraint (see `constraints_violated_by`); `blocked_by` explains why.
+    ConstraintBreakingOnly {
+        newest: String,
+        blocked_by: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct UpgradeReport {
+    pub name: String,
+    pub installed: String,
+    pub verdict: UpgradeVerdict,
+}
+
+/// Reports, for every `(name, version)` in `installed`, what the newest
+/// compatible upgrade (if any) would be.
+pub fn upgrade_report(packages: &[Package], installed: &[(String, String)]) -> Vec<UpgradeReport> {
+    let by_name = resolver::candidates_by_name(packages);
+    let installed_versions: HashMap<&str, &str> = installed
+        .iter()
+        .map(|(name, version)| (name.as_str(), version.as_str()))
+        .collect();
+
+    installed
+        .iter()
+        .map(|(name, version)| {
+            let verdict = upgrade_verdict(packages, &by_name, &installed_versions, name, version);
+            UpgradeReport {
+                name: name.clone(),
+                installed: version.clone(),
+                verdict,
+            }
+        })
+        .collect()
+}
+
+fn upgrade_verdict(
+    packages: &[Package],
+    by_name: &HashMap<String, Vec<&Package>>,
+    installed_versions: &HashMap<&str, &str>,
+    name: &str,
+    installed_version: &str,
+) -> UpgradeVerdict {
+    let Some(candidates) = by_name.get(name) else {
+        return UpgradeVerdict::AlreadyLatest;
+    };
+    let Some(newest) = candidates.first() else {
+        return UpgradeVerdict::AlreadyLatest;
+    };
+    // `candidates` is sorted newest-first, but "newest" only means highest
+    // by `version::compare` - the installed version need not even be a
+    // member (it may have aged out of the archive, or be a local/pinned
+    // build), so it can't be found by equality. Compare properly.
+    if version::compare(&newest.version, installed_version) != Ordering::Greater {
+        return UpgradeVerdict::AlreadyLatest;
+    }
+
+    let mut blocked_by = Vec::new();
+    for candidate in candidates.iter() {
+        if version::compare(&candidate.version, installed_version) != Ordering::Greater {
+            // Nothing newer than the installed version is left to try.
+            break;
+        }
+
+        let violations = constraints_violated_by(packages, installed_versions, name, candidate);
+        if violations.is_empty() {
+            return UpgradeVerdict::CompatibleUpgrade {
+                to: candidate.version.clone(),
+            };
+        }
+        blocked_by.extend(violations);
+    }
+
+    UpgradeVerdict::ConstraintBreakingOnly {
+        newest: newest.version.clone(),
+        blocked_by,
+    }
+}
+
+/// Every reason `candidate` can't replace the currently-installed version
+/// of `name`: each installed package's active `Depends`/`Pre-Depends` edge
+/// on `name` that `candidate`'s version fails to satisfy, each installed
+/// package's `Conflicts`/`Breaks` edge on `name` that `candidate` would
+/// satisfy, any `Conflicts`/`Breaks` `candidate` itself declares
+/// against another installed package, and any `Depends`/`Pre-Depends` of
+/// `candidate`'s own that nothing in the installed set satisfies.
+fn constraints_violated_by(
+    packages: &[Package],
+    installed_versions: &HashMap<&str, &str>,
+    name: &str,
+    candidate: &Package,
+) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    for dependent in packages {
+        if installed_versions.get(dependent.package.as_str()) != Some(&dependent.version.as_str())
+        {
+            continue; // `dependent` isn't part of the installed set
+        }
+
+        for dep in resolver::edges(dependent) {
+            for alt in resolver::alternatives(dep) {
+                if alt.name != name {
+                    continue;
+                }
+                if !resolver::satisfies_alternative(&candidate.version, &alt) {
+                    violations.push(format!(
+                        "{} {} requires {}",
+                        dependent.package,
+                        dependent.version,
+                        resolver::describe(&alt)
+                    ));
+                }
+            }
+        }
+
+        for (via, field) in [
+            ("conflicts with", &dependent.conflicts),
+            ("breaks", &dependent.breaks),
+        ] {
+            for dep in field.iter() {
+                for alt in resolver::alternatives(dep) {
+                    if alt.name != name {
+                        continue;
+                    }
+                    if resolver::satisfies_alternative(&candidate.version, &alt) {
+                        violations.push(format!(
+                            "{} {} {} {}",
+                            dependent.package,
+                            dependent.version,
+                            via,
+                            resolver::describe(&alt)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    for (via, field) in [
+        ("conflicts with", &candidate.conflicts),
+        ("breaks", &candidate.breaks),
+    ] {
+        for dep in field.iter() {
+            for alt in resolver::alternatives(dep) {
+                let Some(&installed_version) = installed_versions.get(alt.name.as_str()) else {
+                    continue;
+                };
+                if resolver::satisfies_alternative(installed_version, &alt) {
+                    violations.push(format!(
+                        "{} {} {} {}",
+                        candidate.package,
+                        candidate.version,
+                        via,
+                        resolver::describe(&alt)
+                    ));
+                }
+            }
+        }
+    }
+
+    for dep in resolver::edges(candidate) {
+        let satisfied = resolver::alternatives(dep).iter().any(|alt| {
+            installed_versions
+                .get(alt.name.as_str())
+                .is_some_and(|v| resolver::satisfies_alternative(v, alt))
+        });
+        if !satisfied {
+            violations.push(format!(
+                "{} {} requires {}",
+                candidate.package,
+                candidate.version,
+                resolver::describe_edge(dep)
+            ));
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use scfs_ddlog::typedefs::{Comparator, Dependency};
+
+    use super::*;
+
+    fn package(name: &str, version: &str) -> Package {
+        Package {
+            package: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn unconstrained(name: &str) -> Dependency {
+        let mut d = Dependency::default();
+        d.package.push(name.to_string());
+        d.version.push(None.into());
+        d
+    }
+
+    #[test]
+    fn already_latest_when_installed_is_the_newest_version() {
+        let packages = vec![package("app", "1")];
+        let installed = vec![("app".to_string(), "1".to_string())];
+
+        let reports = upgrade_report(&packages, &installed);
+        assert_eq!(reports[0].verdict, UpgradeVerdict::AlreadyLatest);
+    }
+
+    #[test]
+    fn compatible_upgrade_when_nothing_blocks_the_newer_version() {
+        let packages = vec![package("app", "1"), package("app", "2")];
+        let installed = vec![("app".to_string(), "1".to_string())];
+
+        let reports = upgrade_report(&packages, &installed);
+        assert_eq!(
+            reports[0].verdict,
+            UpgradeVerdict::CompatibleUpgrade { to: "2".to_string() }
+        );
+    }
+
+    #[test]
+    fn constraint_breaking_only_when_a_depends_edge_would_break() {
+        let mut dependent = package("dependent", "1");
+        let mut on_old_lib = Dependency::default();
+        on_old_lib.package.push("lib".to_string());
+        on_old_lib
+            .version
+            .push(Some((Comparator::EarlierOrEqual, "1".to_string())).into());
+        dependent.depends.push(on_old_lib);
+        let packages = vec![dependent, package("lib", "1"), package("lib", "2")];
+        let installed = vec![
+            ("dependent".to_string(), "1".to_string()),
+            ("lib".to_string(), "1".to_string()),
+        ];
+
+        let reports = upgrade_report(&packages, &installed);
+        let lib_report = reports.iter().find(|r| r.name == "lib").unwrap();
+        match &lib_report.verdict {
+            UpgradeVerdict::ConstraintBreakingOnly { newest, blocked_by } => {
+                assert_eq!(newest, "2");
+                assert!(!blocked_by.is_empty());
+            }
+            other => panic!("expected ConstraintBreakingOnly, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn constraint_breaking_only_when_the_newer_version_introduces_a_conflict() {
+        let mut new_lib = package("lib", "2");
+        new_lib.conflicts.push(unconstrained("other"));
+        let packages = vec![package("lib", "1"), new_lib, package("other", "1")];
+        let installed = vec![
+            ("lib".to_string(), "1".to_string()),
+            ("other".to_string(), "1".to_string()),
+        ];
+
+        let reports = upgrade_report(&packages, &installed);
+        let lib_report = reports.iter().find(|r| r.name == "lib").unwrap();
+        match &lib_report.verdict {
+            UpgradeVerdict::ConstraintBreakingOnly { newest, blocked_by } => {
+                assert_eq!(newest, "2");
+                assert!(!blocked_by.is_empty());
+            }
+            other => panic!("expected ConstraintBreakingOnly, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn already_latest_when_installed_version_is_not_in_the_archive_but_is_newest() {
+        // The installed version (e.g. a local/pinned build, or one that
+        // aged out of the index) isn't a candidate at all, and every
+        // candidate that is present is older - this must not be reported
+        // as a CompatibleUpgrade (a downgrade) just because it's the first
+        // candidate found.
+        let packages = vec![package("app", "1"), package("app", "2")];
+        let installed = vec![("app".to_string(), "10".to_string())];
+
+        let reports = upgrade_report(&packages, &installed);
+        assert_eq!(reports[0].verdict, UpgradeVerdict::AlreadyLatest);
+    }
+
+    #[test]
+    fn constraint_breaking_only_when_the_candidate_s_own_dependency_is_unmet() {
+        let mut new_app = package("app", "2");
+        new_app.depends.push(unconstrained("lib"));
+        let packages = vec![package("app", "1"), new_app];
+        let installed = vec![("app".to_string(), "1".to_string())];
+
+        let reports = upgrade_report(&packages, &installed);
+        match &reports[0].verdict {
+            UpgradeVerdict::ConstraintBreakingOnly { newest, blocked_by } => {
+                assert_eq!(newest, "2");
+                assert!(!blocked_by.is_empty());
+            }
+            other => panic!("expected ConstraintBreakingOnly, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Debian version comparison (policy §5.6.12), used to evaluate the
+//! `Comparator` constraints that `parse::parse_package` attaches to each
+//! `Dependency`.
+//!
+//! A version is `[epoch:]upstream[-revision]`. Epochs compare as integers;
+//! `upstream` and `revision` both compare with the same "run" algorithm:
+//! walk the strings as alternating runs of non-digit and digit characters,
+//! always starting with a (possibly empty) non-digit run.
+
+use std::cmp::Ordering;
+
+use scfs_ddlog::typedefs::Comparator;
+
+/// Splits `version` into `(epoch, upstream, revision)`. A missing epoch is
+/// `0`; a missing revision is `"0"`.
+fn split_version(version: &str) -> (u64, &str, &str) {
+    let (epoch, rest) = match version.find(':') {
+        Some(idx) => (version[..idx].parse().unwrap_or(0), &version[idx + 1..]),
+        None => (0, version),
+    };
+    match rest.rfind('-') {
+        Some(idx) => (epoch, &rest[..idx], &rest[idx + 1..]),
+        None => (epoch, rest, "0"),
+    }
+}
+
+/// dpkg's modified ordering for a single character of a non-digit run: `~`
+/// sorts before everything, including the end of the run; letters sort
+/// before all other non-letter ASCII; otherwise plain byte order applies.
+fn order(c: Option<char>) -> i32 {
+    match c {
+        None => 0,
+        Some('~') => -1,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+fn compare_non_digit_run(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars();
+    let mut b = b.chars();
+    loop {
+        let (ca, cb) = (a.next(), b.next());
+        if ca.is_none() && cb.is_none() {
+            return Ordering::Equal;
+        }
+        match order(ca).cmp(&order(cb)) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+}
+
+/// Digit runs compare numerically: strip leading zeros, then the longer run
+/// wins; equal-length runs fall back to lexicographic (== numeric) order.
+fn compare_digit_run(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Splits the non-digit (or digit, if `digit` is set) run off the front of
+/// `s` and returns `(run, rest)`.
+fn take_run(s: &str, digit: bool) -> (&str, &str) {
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digit)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn compare_component(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        let (a_run, a_rest) = take_run(a, false);
+        let (b_run, b_rest) = take_run(b, false);
+        match compare_non_digit_run(a_run, b_run) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+        a = a_rest;
+        b = b_rest;
+        if a.is_empty() && b.is_empty() {
+            return Ordering::Equal;
+        }
+
+        let (a_run, a_rest) = take_run(a, true);
+        let (b_run, b_rest) = take_run(b, true);
+        match compare_digit_run(a_run, b_run) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+        a = a_rest;
+        b = b_rest;
+        if a.is_empty() && b.is_empty() {
+            return Ordering::Equal;
+        }
+    }
+}
+
+/// Orders two Debian version strings following the dpkg algorithm.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let (epoch_a, upstream_a, revision_a) = split_version(a);
+    let (epoch_b, upstream_b, revision_b) = split_version(b);
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| compare_component(upstream_a, upstream_b))
+        .then_with(|| compare_component(revision_a, revision_b))
+}
+
+/// Does `candidate` satisfy `cmp bound` (e.g. `candidate >= bound` for
+/// `Comparator::LaterOrEqual`)?
+pub fn satisfies(candidate: &str, cmp: Comparator, bound: &str) -> bool {
+    let ordering = compare(candidate, bound);
+    match cmp {
+        Comparator::StrictlyEarlier => ordering == Ordering::Less,
+        Comparator::EarlierOrEqual => ordering != Ordering::Greater,
+        Comparator::ExactlyEqual => ordering == Ordering::Equal,
+        Comparator::LaterOrEqual => ordering != Ordering::Less,
+        Comparator::StrictlyLater => ordering == Ordering::Greater,
+    }
+}
+
+/// Mirrors the `extern function version_satisfies` declared in `scfs.dl`, so
+/// DDlog rules can evaluate `Depends`/`Conflicts` constraints directly.
+/// DDlog's generated call site passes its `string` values as `&String`, so
+/// the signature has to take that rather than `&str`.
+#[allow(clippy::ptr_arg)]
+pub fn version_satisfies(candidate: &String, cmp: Comparator, bound: &String) -> bool {
+    satisfies(candidate, cmp, bound)
+}